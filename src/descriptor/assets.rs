@@ -0,0 +1,389 @@
+// Miniscript
+// Written in 2020 by rust-miniscript developers
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! # Spending Assets
+//!
+//! An [`Assets`] describes what is actually available at spend time: which
+//! public keys a signature can be produced for, which hash preimages are
+//! known, and what height / median-time-past the spending transaction will be
+//! confirmed at. It's used by [`super::segwitv0::Wsh::max_satisfaction_weight_assuming`]
+//! to prune miniscript branches that can never be satisfied with those assets,
+//! so that fee estimation reflects the spend path that will actually be used
+//! instead of the worst case across every alternative (including branches,
+//! like a multi-year timelocked fallback, that in practice are never taken).
+
+use std::collections::HashSet;
+
+use bitcoin::hashes::{hash160, ripemd160, sha256, sha256d};
+
+use crate::policy::semantic::Policy;
+use crate::MiniscriptKey;
+
+// Worst-case size, in bytes, of a DER-encoded ECDSA signature plus its
+// trailing sighash-type byte (matches the `73` used by `Wpkh::max_satisfaction_weight`).
+const ECDSA_SIG_SIZE: usize = 73;
+
+// Size, in bytes, of a compressed public key revealed alongside a `pkh`
+// signature (matches `Segwitv0::pk_len` for a compressed key; `Assets` only
+// has the key's hash to go on, not the key itself, so the uncompressed case
+// can't be distinguished here).
+const COMPRESSED_PUBKEY_SIZE: usize = 33;
+
+// Sizes, in bytes, of the preimages revealed by each hash fragment.
+const SHA256_PREIMAGE_SIZE: usize = 32;
+const HASH256_PREIMAGE_SIZE: usize = 32;
+const RIPEMD160_PREIMAGE_SIZE: usize = 20;
+const HASH160_PREIMAGE_SIZE: usize = 20;
+
+/// The assets available when estimating a descriptor's satisfaction weight.
+#[derive(Clone, Debug)]
+pub struct Assets<Pk: MiniscriptKey> {
+    /// Public keys a signature can be produced for.
+    pub keys: HashSet<Pk>,
+    /// `sha256` preimages that are known.
+    pub sha256_preimages: HashSet<sha256::Hash>,
+    /// `hash256` preimages that are known.
+    pub hash256_preimages: HashSet<sha256d::Hash>,
+    /// `ripemd160` preimages that are known.
+    pub ripemd160_preimages: HashSet<ripemd160::Hash>,
+    /// `hash160` preimages that are known.
+    pub hash160_preimages: HashSet<hash160::Hash>,
+    /// The block height the spend is assumed to confirm at, used to decide
+    /// whether `after` (`OP_CHECKLOCKTIMEVERIFY`) timelocks have matured.
+    pub current_height: Option<u32>,
+    /// The input's age (in blocks or 512-second units, per BIP68) the spend is
+    /// assumed to have, used to decide whether `older` (`OP_CHECKSEQUENCEVERIFY`)
+    /// timelocks have matured.
+    pub current_sequence: Option<u32>,
+}
+
+impl<Pk: MiniscriptKey> Assets<Pk> {
+    /// An empty set of assets: nothing is assumed available, so only branches
+    /// requiring no signature, preimage or timelock are considered satisfiable.
+    pub fn new() -> Self {
+        Self {
+            keys: HashSet::new(),
+            sha256_preimages: HashSet::new(),
+            hash256_preimages: HashSet::new(),
+            ripemd160_preimages: HashSet::new(),
+            hash160_preimages: HashSet::new(),
+            current_height: None,
+            current_sequence: None,
+        }
+    }
+
+    /// Assume a signature can be produced for `key`.
+    pub fn add_key(mut self, key: Pk) -> Self {
+        self.keys.insert(key);
+        self
+    }
+
+    /// Assume the `sha256` preimage of `hash` is known.
+    pub fn add_sha256_preimage(mut self, hash: sha256::Hash) -> Self {
+        self.sha256_preimages.insert(hash);
+        self
+    }
+
+    /// Assume the `hash256` preimage of `hash` is known.
+    pub fn add_hash256_preimage(mut self, hash: sha256d::Hash) -> Self {
+        self.hash256_preimages.insert(hash);
+        self
+    }
+
+    /// Assume the `ripemd160` preimage of `hash` is known.
+    pub fn add_ripemd160_preimage(mut self, hash: ripemd160::Hash) -> Self {
+        self.ripemd160_preimages.insert(hash);
+        self
+    }
+
+    /// Assume the `hash160` preimage of `hash` is known.
+    pub fn add_hash160_preimage(mut self, hash: hash160::Hash) -> Self {
+        self.hash160_preimages.insert(hash);
+        self
+    }
+
+    /// Assume the spend confirms at `height`, maturing any `after` timelock it clears.
+    pub fn at_height(mut self, height: u32) -> Self {
+        self.current_height = Some(height);
+        self
+    }
+
+    /// Assume the spent input has `sequence` age, maturing any `older` timelock it clears.
+    pub fn with_sequence(mut self, sequence: u32) -> Self {
+        self.current_sequence = Some(sequence);
+        self
+    }
+}
+
+impl<Pk: MiniscriptKey> Default for Assets<Pk> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Pk: MiniscriptKey> Assets<Pk> {
+    /// Computes `(witness_element_count, witness_byte_count)` for the cheapest
+    /// spend of `policy` that is satisfiable with these assets, or `None` if no
+    /// satisfiable spend exists at all.
+    ///
+    /// This is the pruning step requested for `max_satisfaction_weight_assuming`:
+    /// a `Key` (`pk`/`multi` leaf) node is only satisfiable if the relevant keys
+    /// are in [`Assets::keys`]; an `After`/`Older` node only if `current_height`/
+    /// `current_sequence` clears its threshold; unsatisfiable subtrees are
+    /// pruned entirely, and `Or`/`Threshold` nodes take the maximum cost among
+    /// only the alternatives that remain.
+    ///
+    /// A `pkh` fragment is satisfiable only if one of [`Assets::keys`] hashes
+    /// to the given hash, and costs a signature *and* the revealed pubkey (two
+    /// witness elements), since only the hash is committed on-chain. Each hash
+    /// fragment (`sha256`/`hash256`/`ripemd160`/`hash160`) is satisfiable only
+    /// if its preimage is in the matching [`Assets`] preimage set, and costs a
+    /// single witness element sized to that hash's preimage (32/32/20/20
+    /// bytes), not a signature.
+    pub(crate) fn satisfaction_cost(&self, policy: &Policy<Pk>) -> Option<(usize, usize)> {
+        match *policy {
+            Policy::Unsatisfiable => None,
+            Policy::Trivial => Some((0, 0)),
+            Policy::Key(ref pk) => {
+                if self.keys.contains(pk) {
+                    Some((1, ECDSA_SIG_SIZE))
+                } else {
+                    None
+                }
+            }
+            Policy::After(t) => {
+                if self.current_height.map_or(false, |h| h >= t) {
+                    Some((0, 0))
+                } else {
+                    None
+                }
+            }
+            Policy::Older(t) => {
+                if self.current_sequence.map_or(false, |s| s >= t) {
+                    Some((0, 0))
+                } else {
+                    None
+                }
+            }
+            Policy::KeyHash(ref h) => {
+                if self.keys.iter().any(|k| k.to_pubkeyhash() == *h) {
+                    Some((2, ECDSA_SIG_SIZE + COMPRESSED_PUBKEY_SIZE))
+                } else {
+                    None
+                }
+            }
+            Policy::Sha256(ref h) => {
+                if self.sha256_preimages.contains(h) {
+                    Some((1, SHA256_PREIMAGE_SIZE))
+                } else {
+                    None
+                }
+            }
+            Policy::Hash256(ref h) => {
+                if self.hash256_preimages.contains(h) {
+                    Some((1, HASH256_PREIMAGE_SIZE))
+                } else {
+                    None
+                }
+            }
+            Policy::Ripemd160(ref h) => {
+                if self.ripemd160_preimages.contains(h) {
+                    Some((1, RIPEMD160_PREIMAGE_SIZE))
+                } else {
+                    None
+                }
+            }
+            Policy::Hash160(ref h) => {
+                if self.hash160_preimages.contains(h) {
+                    Some((1, HASH160_PREIMAGE_SIZE))
+                } else {
+                    None
+                }
+            }
+            Policy::And(ref subs) => {
+                let mut elems = 0;
+                let mut bytes = 0;
+                for sub in subs {
+                    let (e, b) = self.satisfaction_cost(sub)?;
+                    elems += e;
+                    bytes += b;
+                }
+                Some((elems, bytes))
+            }
+            Policy::Or(ref subs) => subs
+                .iter()
+                .filter_map(|(_prob, sub)| self.satisfaction_cost(sub))
+                .max_by_key(|&(e, b)| e + b),
+            Policy::Threshold(k, ref subs) => {
+                let mut costs: Vec<(usize, usize)> = subs
+                    .iter()
+                    .filter_map(|sub| self.satisfaction_cost(sub))
+                    .collect();
+                if costs.len() < k {
+                    return None;
+                }
+                // The worst case for a k-of-n threshold is whichever k branches
+                // are the most expensive to satisfy.
+                costs.sort_unstable_by(|a, b| (b.0 + b.1).cmp(&(a.0 + a.1)));
+                let (elems, bytes) = costs[..k]
+                    .iter()
+                    .fold((0, 0), |(e, b), &(ce, cb)| (e + ce, b + cb));
+                Some((elems, bytes))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bitcoin::hashes::Hash;
+    use bitcoin::PublicKey;
+
+    use super::*;
+
+    fn key_a() -> PublicKey {
+        PublicKey::from_str("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+            .unwrap()
+    }
+    fn key_b() -> PublicKey {
+        PublicKey::from_str("03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd")
+            .unwrap()
+    }
+    fn key_c() -> PublicKey {
+        PublicKey::from_str("02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5")
+            .unwrap()
+    }
+
+    #[test]
+    fn key_requires_matching_asset() {
+        let assets = Assets::new().add_key(key_a());
+        assert!(assets.satisfaction_cost(&Policy::Key(key_a())).is_some());
+        assert!(assets.satisfaction_cost(&Policy::Key(key_b())).is_none());
+    }
+
+    #[test]
+    fn keyhash_requires_matching_asset_and_costs_sig_plus_pubkey() {
+        let assets = Assets::new().add_key(key_a());
+        let policy = Policy::KeyHash(key_a().to_pubkeyhash());
+        assert_eq!(
+            assets.satisfaction_cost(&policy),
+            Some((2, ECDSA_SIG_SIZE + COMPRESSED_PUBKEY_SIZE))
+        );
+
+        let other_policy = Policy::KeyHash(key_b().to_pubkeyhash());
+        assert!(assets.satisfaction_cost(&other_policy).is_none());
+    }
+
+    #[test]
+    fn hash_fragments_require_matching_preimage_and_cost_the_preimage_size() {
+        let sha256_hash = sha256::Hash::hash(b"sha256");
+        let hash256_hash = sha256d::Hash::hash(b"hash256");
+        let ripemd160_hash = ripemd160::Hash::hash(b"ripemd160");
+        let hash160_hash = hash160::Hash::hash(b"hash160");
+
+        let assets = Assets::<PublicKey>::new()
+            .add_sha256_preimage(sha256_hash)
+            .add_hash256_preimage(hash256_hash)
+            .add_ripemd160_preimage(ripemd160_hash)
+            .add_hash160_preimage(hash160_hash);
+
+        assert_eq!(
+            assets.satisfaction_cost(&Policy::Sha256(sha256_hash)),
+            Some((1, SHA256_PREIMAGE_SIZE))
+        );
+        assert_eq!(
+            assets.satisfaction_cost(&Policy::Hash256(hash256_hash)),
+            Some((1, HASH256_PREIMAGE_SIZE))
+        );
+        assert_eq!(
+            assets.satisfaction_cost(&Policy::Ripemd160(ripemd160_hash)),
+            Some((1, RIPEMD160_PREIMAGE_SIZE))
+        );
+        assert_eq!(
+            assets.satisfaction_cost(&Policy::Hash160(hash160_hash)),
+            Some((1, HASH160_PREIMAGE_SIZE))
+        );
+
+        // An empty `Assets` holds none of the preimages, so every hash
+        // fragment is unsatisfiable.
+        let empty = Assets::<PublicKey>::new();
+        assert!(empty.satisfaction_cost(&Policy::Sha256(sha256_hash)).is_none());
+        assert!(empty
+            .satisfaction_cost(&Policy::Hash256(hash256_hash))
+            .is_none());
+        assert!(empty
+            .satisfaction_cost(&Policy::Ripemd160(ripemd160_hash))
+            .is_none());
+        assert!(empty
+            .satisfaction_cost(&Policy::Hash160(hash160_hash))
+            .is_none());
+    }
+
+    #[test]
+    fn after_and_older_require_cleared_threshold() {
+        let assets = Assets::<PublicKey>::new().at_height(100).with_sequence(10);
+        assert!(assets.satisfaction_cost(&Policy::After(100)).is_some());
+        assert!(assets.satisfaction_cost(&Policy::After(101)).is_none());
+        assert!(assets.satisfaction_cost(&Policy::Older(10)).is_some());
+        assert!(assets.satisfaction_cost(&Policy::Older(11)).is_none());
+    }
+
+    #[test]
+    fn or_prunes_unsatisfiable_branch_before_taking_the_max() {
+        let assets = Assets::new().add_key(key_a());
+
+        // Branch 1: a single available key. Branch 2: a 2-of-2 multisig of keys
+        // that aren't available. Without pruning, a naive "max over all
+        // branches" would either pick branch 2's (unsatisfiable!) cost or bail
+        // out entirely; pruning must fall through to branch 1's real cost.
+        let policy = Policy::Or(vec![
+            (1, Policy::Key(key_a())),
+            (1, Policy::And(vec![Policy::Key(key_b()), Policy::Key(key_c())])),
+        ]);
+
+        let cost = assets
+            .satisfaction_cost(&policy)
+            .expect("branch 1 is satisfiable");
+        assert_eq!(cost, (1, ECDSA_SIG_SIZE));
+    }
+
+    #[test]
+    fn or_is_unsatisfiable_if_every_branch_is() {
+        let assets = Assets::<PublicKey>::new();
+        let policy = Policy::Or(vec![(1, Policy::Key(key_a())), (1, Policy::Key(key_b()))]);
+        assert!(assets.satisfaction_cost(&policy).is_none());
+    }
+
+    #[test]
+    fn threshold_needs_k_satisfiable_branches() {
+        let two_of_three = Policy::Threshold(
+            2,
+            vec![
+                Policy::Key(key_a()),
+                Policy::Key(key_b()),
+                Policy::Key(key_c()),
+            ],
+        );
+
+        let two_keys = Assets::new().add_key(key_a()).add_key(key_b());
+        assert_eq!(
+            two_keys.satisfaction_cost(&two_of_three),
+            Some((2, 2 * ECDSA_SIG_SIZE))
+        );
+
+        let one_key = Assets::new().add_key(key_a());
+        assert!(one_key.satisfaction_cost(&two_of_three).is_none());
+    }
+}
@@ -15,16 +15,23 @@
 //!
 //! Implementation of Segwit Descriptors. Contains the implementation
 //! of wsh, wpkh and sortedmulti inside wsh.
+//!
+//! `Wsh` normally only accepts "sane" (non-malleable, safe) miniscripts; the
+//! `new_insane`/`from_str_insane` family of constructors relaxes those
+//! heuristics (see [`crate::miniscript::context::ExtParams`]) so that arbitrary
+//! consensus-valid witness scripts can be parsed and inspected. Run
+//! [`Wsh::sanity_check`] before treating such a descriptor as spendable.
 
 use std::fmt;
 use std::str::FromStr;
 
 use bitcoin::{self, Address, Network, Script};
 
+use super::assets::Assets;
 use super::checksum::{desc_checksum, verify_checksum};
 use super::{DescriptorTrait, SortedMultiVec};
 use crate::expression::{self, FromTree};
-use crate::miniscript::context::{ScriptContext, ScriptContextError};
+use crate::miniscript::context::{ExtParams, ScriptContext, ScriptContextError};
 use crate::policy::{semantic, Liftable};
 use crate::util::varint_len;
 use crate::{
@@ -58,6 +65,20 @@ impl<Pk: MiniscriptKey> Wsh<Pk> {
         })
     }
 
+    /// Create a new wsh descriptor from a miniscript that may fail the usual
+    /// sanity/non-malleability heuristics (e.g. one recovered from a third-party
+    /// wallet or an adversarial UTXO). The maximum witness script size is still
+    /// enforced; stack element count and opcode count are not checked here.
+    ///
+    /// The result is not guaranteed to be safe to satisfy; callers must run
+    /// [`Wsh::sanity_check`] before treating it as spendable.
+    pub fn new_insane(ms: Miniscript<Pk, Segwitv0>) -> Result<Self, Error> {
+        Segwitv0::top_level_checks_ext(&ms, ExtParams::insane())?;
+        Ok(Self {
+            inner: WshInner::Ms(ms),
+        })
+    }
+
     /// Create a new sortedmulti wsh descriptor
     pub fn new_sortedmulti(k: usize, pks: Vec<Pk>) -> Result<Self, Error> {
         // The context checks will be carried out inside new function for
@@ -83,6 +104,34 @@ impl<Pk: MiniscriptKey> Wsh<Pk> {
         }
         Ok(())
     }
+
+    /// Like [`DescriptorTrait::max_satisfaction_weight`], but takes the maximum
+    /// only over branches that are satisfiable with the given `assets`
+    /// (available keys, known hash preimages, and an assumed height / sequence),
+    /// instead of over every alternative in the miniscript.
+    ///
+    /// This avoids overestimating fees for descriptors where, in practice, only
+    /// one spend path is ever taken (say, the 2-of-3 multisig) while another
+    /// (say, a timelocked recovery key) never is: a `pk`/`multi` node is treated
+    /// as satisfiable only if enough of its keys are in `assets`, and an
+    /// `after`/`older` node only if `assets`' height/sequence clears its
+    /// threshold, pruning unsatisfiable subtrees before taking the max over any
+    /// `or`/`thresh` alternatives.
+    pub fn max_satisfaction_weight_assuming(&self, assets: &Assets<Pk>) -> Result<usize, Error> {
+        let script_size = match self.inner {
+            WshInner::SortedMulti(ref smv) => smv.script_size(),
+            WshInner::Ms(ref ms) => ms.script_size(),
+        };
+        let policy = self.lift()?;
+        let (max_sat_elems, max_sat_size) = assets
+            .satisfaction_cost(&policy)
+            .ok_or(Error::CouldNotSatisfy)?;
+        Ok(4 +  // scriptSig length byte
+            varint_len(script_size) +
+            script_size +
+            varint_len(max_sat_elems) +
+            max_sat_size)
+    }
 }
 
 impl<Pk: MiniscriptKey + ToPublicKey> Wsh<Pk> {
@@ -134,14 +183,16 @@ impl<Pk: MiniscriptKey> Liftable<Pk> for Wsh<Pk> {
     }
 }
 
-impl<Pk> FromTree for Wsh<Pk>
+impl<Pk> Wsh<Pk>
 where
     Pk: MiniscriptKey + FromStr,
     Pk::Hash: FromStr,
     <Pk as FromStr>::Err: ToString,
     <<Pk as MiniscriptKey>::Hash as FromStr>::Err: ToString,
 {
-    fn from_tree(top: &expression::Tree) -> Result<Self, Error> {
+    /// Parse a `wsh` descriptor tree under the given [`ExtParams`], instead of the
+    /// sane-only defaults used by [`FromTree::from_tree`].
+    pub fn from_tree_ext(top: &expression::Tree, ext: ExtParams) -> Result<Self, Error> {
         if top.name == "wsh" && top.args.len() == 1 {
             let top = &top.args[0];
             if top.name == "sortedmulti" {
@@ -150,7 +201,7 @@ where
                 });
             }
             let sub = Miniscript::from_tree(top)?;
-            Segwitv0::top_level_checks(&sub)?;
+            Segwitv0::top_level_checks_ext(&sub, ext)?;
             Ok(Wsh {
                 inner: WshInner::Ms(sub),
             })
@@ -162,6 +213,37 @@ where
             )))
         }
     }
+
+    /// Parse a `wsh(...)#checksum` descriptor string under the given
+    /// [`ExtParams`], instead of the sane-only defaults used by [`FromStr::from_str`].
+    pub fn from_str_ext(s: &str, ext: ExtParams) -> Result<Self, Error> {
+        let desc_str = verify_checksum(s)?;
+        let top = expression::Tree::from_str(desc_str)?;
+        Self::from_tree_ext(&top, ext)
+    }
+
+    /// Parse a `wsh(...)#checksum` descriptor string, skipping the sanity and
+    /// non-malleability checks normally enforced while parsing. The maximum
+    /// witness script size is still enforced; stack element count and opcode
+    /// count are not checked here.
+    ///
+    /// The result is not guaranteed to be safe to satisfy; callers must run
+    /// [`Wsh::sanity_check`] before treating it as spendable.
+    pub fn from_str_insane(s: &str) -> Result<Self, Error> {
+        Self::from_str_ext(s, ExtParams::insane())
+    }
+}
+
+impl<Pk> FromTree for Wsh<Pk>
+where
+    Pk: MiniscriptKey + FromStr,
+    Pk::Hash: FromStr,
+    <Pk as FromStr>::Err: ToString,
+    <<Pk as MiniscriptKey>::Hash as FromStr>::Err: ToString,
+{
+    fn from_tree(top: &expression::Tree) -> Result<Self, Error> {
+        Self::from_tree_ext(top, ExtParams::new())
+    }
 }
 impl<Pk: MiniscriptKey> fmt::Debug for Wsh<Pk> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -528,3 +610,70 @@ where
         Ok(Wpkh::new(fpk(&self.pk)?).expect("Uncompressed keys in Wpkh"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A bare `after` has no top-level signature requirement: anyone can sweep
+    // the output once the locktime passes. `sanity_check`/`new` reject this as
+    // unsafe; `new_insane` accepts it anyway since it's still consensus-valid.
+    fn unsafe_top_level_ms() -> Miniscript<bitcoin::PublicKey, Segwitv0> {
+        Miniscript::<bitcoin::PublicKey, Segwitv0>::from_str("after(100)")
+            .expect("after(100) is a consensus-valid miniscript fragment")
+    }
+
+    #[test]
+    fn new_rejects_unsafe_top_level() {
+        assert!(Wsh::new(unsafe_top_level_ms()).is_err());
+    }
+
+    #[test]
+    fn new_insane_accepts_unsafe_top_level_but_sanity_check_still_rejects_it() {
+        let wsh = Wsh::new_insane(unsafe_top_level_ms())
+            .expect("new_insane should accept an unsafe top level");
+        assert!(wsh.sanity_check().is_err());
+    }
+
+    #[test]
+    fn from_tree_ext_insane_matches_new_insane() {
+        let ms = unsafe_top_level_ms();
+        let tree = expression::Tree::from_str(&format!("wsh({})", ms)).unwrap();
+
+        assert!(Wsh::<bitcoin::PublicKey>::from_tree_ext(&tree, ExtParams::new()).is_err());
+        assert!(Wsh::<bitcoin::PublicKey>::from_tree_ext(&tree, ExtParams::insane()).is_ok());
+    }
+
+    // A top-level `pkh` still requires a signature, so it passes `sanity_check`
+    // fine; it's rejected only because its preimage public key is unknown.
+    // This is a distinct toggle from `allow_unsafe`, so it must be settable on
+    // its own.
+    fn raw_pkh_ms() -> Miniscript<bitcoin::PublicKey, Segwitv0> {
+        Miniscript::<bitcoin::PublicKey, Segwitv0>::from_str(
+            "pkh(eb6a9c79934c5a37a2f88fc51cd4eddb6a91e5a7)",
+        )
+        .expect("pkh(HASH) is a consensus-valid miniscript fragment")
+    }
+
+    #[test]
+    fn new_rejects_raw_pkh_but_new_insane_accepts_it() {
+        assert!(Wsh::new(raw_pkh_ms()).is_err());
+        let wsh = Wsh::new_insane(raw_pkh_ms())
+            .expect("new_insane should accept a raw pkh fragment");
+        // The signature requirement is intact, so sanity_check still passes.
+        assert!(wsh.sanity_check().is_ok());
+    }
+
+    #[test]
+    fn raw_pkh_toggle_is_independent_of_unsafe_top_level() {
+        let ms = raw_pkh_ms();
+        let tree = expression::Tree::from_str(&format!("wsh({})", ms)).unwrap();
+
+        assert!(Wsh::<bitcoin::PublicKey>::from_tree_ext(&tree, ExtParams::new()).is_err());
+        assert!(Wsh::<bitcoin::PublicKey>::from_tree_ext(
+            &tree,
+            ExtParams::new().raw_pkh(true)
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,631 @@
+// Miniscript
+// Written in 2020 by rust-miniscript developers
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! # Taproot Descriptors
+//!
+//! Implementation of Taproot Descriptors. Contains the implementation
+//! of `tr` including the internal key and an optional tree of leaf
+//! miniscripts under script-path spends.
+
+use std::cmp;
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use bitcoin::blockdata::opcodes::all::OP_PUSHNUM_1;
+use bitcoin::blockdata::script::Builder;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::{self, Scalar, Secp256k1};
+use bitcoin::util::address::{Payload, WitnessVersion};
+use bitcoin::{Address, Network, Script, XOnlyPublicKey};
+
+use super::checksum::{desc_checksum, verify_checksum};
+use super::DescriptorTrait;
+use crate::expression::{self, FromTree};
+use crate::policy::{semantic, Liftable};
+use crate::util::varint_len;
+use crate::{Error, ForEach, ForEachKey, Miniscript, MiniscriptKey, Satisfier, Tap, ToPublicKey, TranslatePk};
+
+// The leaf version for tapscript leaves, as defined by BIP341.
+const LEAF_VERSION_TAPSCRIPT: u8 = 0xc0;
+// BIP341 caps the depth of the taptree at 128 levels.
+const TAPROOT_CONTROL_MAX_NODE_COUNT: usize = 128;
+
+// Compute `tagged_hash(tag, data)` as defined in BIP340: sha256(sha256(tag) || sha256(tag) || data)
+fn tagged_hash(tag: &str, data: &[&[u8]]) -> sha256::Hash {
+    let tag_hash = sha256::Hash::hash(tag.as_bytes());
+    let mut engine = sha256::Hash::engine();
+    engine.input(&tag_hash[..]);
+    engine.input(&tag_hash[..]);
+    for d in data {
+        engine.input(d);
+    }
+    sha256::Hash::from_engine(engine)
+}
+
+fn leaf_hash(script: &Script) -> sha256::Hash {
+    let mut ser = Vec::with_capacity(1 + 9 + script.len());
+    ser.push(LEAF_VERSION_TAPSCRIPT);
+    write_compact_size(&mut ser, script.len() as u64);
+    ser.extend(script.as_bytes());
+    tagged_hash("TapLeaf", &[&ser])
+}
+
+fn branch_hash(a: &sha256::Hash, b: &sha256::Hash) -> sha256::Hash {
+    if a[..] <= b[..] {
+        tagged_hash("TapBranch", &[&a[..], &b[..]])
+    } else {
+        tagged_hash("TapBranch", &[&b[..], &a[..]])
+    }
+}
+
+fn write_compact_size(buf: &mut Vec<u8>, n: u64) {
+    if n < 0xfd {
+        buf.push(n as u8);
+    } else if n <= 0xffff {
+        buf.push(0xfd);
+        buf.extend(&(n as u16).to_le_bytes());
+    } else if n <= 0xffff_ffff {
+        buf.push(0xfe);
+        buf.extend(&(n as u32).to_le_bytes());
+    } else {
+        buf.push(0xff);
+        buf.extend(&n.to_le_bytes());
+    }
+}
+
+/// A Taproot tree of leaf miniscripts, as used in a `Tr` descriptor's script path.
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum TapTree<Pk: MiniscriptKey> {
+    /// A branch joining two sub-trees
+    Tree(Rc<TapTree<Pk>>, Rc<TapTree<Pk>>),
+    /// A leaf script, spendable by satisfying its miniscript
+    Leaf(Rc<Miniscript<Pk, Tap>>),
+}
+
+impl<Pk: MiniscriptKey> TapTree<Pk> {
+    // Depth of the tree, used to enforce BIP341's 128 level limit.
+    fn taproot_depth(&self) -> usize {
+        match *self {
+            TapTree::Leaf(..) => 0,
+            TapTree::Tree(ref left, ref right) => {
+                1 + cmp::max(left.taproot_depth(), right.taproot_depth())
+            }
+        }
+    }
+}
+
+impl<Pk: MiniscriptKey + ToPublicKey> TapTree<Pk> {
+    // The merkle root of this (sub)tree, computed bottom-up per BIP341.
+    fn merkle_root(&self) -> sha256::Hash {
+        match *self {
+            TapTree::Leaf(ref ms) => leaf_hash(&ms.encode()),
+            TapTree::Tree(ref left, ref right) => {
+                branch_hash(&left.merkle_root(), &right.merkle_root())
+            }
+        }
+    }
+
+    // All leaves of the tree, together with the merkle proof (sibling hashes, in
+    // leaf-to-root order) required to build that leaf's control block.
+    fn leaves(&self) -> Vec<(Rc<Miniscript<Pk, Tap>>, Vec<sha256::Hash>)> {
+        let mut leaves = vec![];
+        self.collect_leaves(vec![], &mut leaves);
+        leaves
+    }
+
+    fn collect_leaves(
+        &self,
+        path: Vec<sha256::Hash>,
+        out: &mut Vec<(Rc<Miniscript<Pk, Tap>>, Vec<sha256::Hash>)>,
+    ) {
+        match *self {
+            TapTree::Leaf(ref ms) => out.push((Rc::clone(ms), path)),
+            TapTree::Tree(ref left, ref right) => {
+                let left_hash = left.merkle_root();
+                let right_hash = right.merkle_root();
+
+                let mut left_path = path.clone();
+                left_path.insert(0, right_hash);
+                left.collect_leaves(left_path, out);
+
+                let mut right_path = path;
+                right_path.insert(0, left_hash);
+                right.collect_leaves(right_path, out);
+            }
+        }
+    }
+}
+
+impl<Pk: MiniscriptKey> fmt::Display for TapTree<Pk> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TapTree::Tree(ref left, ref right) => write!(f, "{{{},{}}}", left, right),
+            TapTree::Leaf(ref ms) => write!(f, "{}", ms),
+        }
+    }
+}
+
+impl<Pk: MiniscriptKey> fmt::Debug for TapTree<Pk> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl<Pk> TapTree<Pk>
+where
+    Pk: MiniscriptKey + FromStr,
+    Pk::Hash: FromStr,
+    <Pk as FromStr>::Err: ToString,
+    <<Pk as MiniscriptKey>::Hash as FromStr>::Err: ToString,
+{
+    // A braced group `{A,B}` parses as an unnamed node with two args; anything
+    // else is a leaf miniscript fragment.
+    fn from_tree(tree: &expression::Tree) -> Result<Self, Error> {
+        if tree.name.is_empty() && tree.args.len() == 2 {
+            let left = TapTree::from_tree(&tree.args[0])?;
+            let right = TapTree::from_tree(&tree.args[1])?;
+            Ok(TapTree::Tree(Rc::new(left), Rc::new(right)))
+        } else {
+            let ms = Miniscript::<Pk, Tap>::from_tree(tree)?;
+            Ok(TapTree::Leaf(Rc::new(ms)))
+        }
+    }
+}
+
+/// A Taproot (`tr`) descriptor: an internal key, with an optional tree of
+/// alternative leaf miniscripts spendable via the script path.
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct Tr<Pk: MiniscriptKey> {
+    /// The internal key of this Taproot output
+    internal_key: Pk,
+    /// The tree of script-path spends, if any
+    tree: Option<TapTree<Pk>>,
+}
+
+impl<Pk: MiniscriptKey> Tr<Pk> {
+    /// Create a new Taproot descriptor from an internal key and an optional leaf tree
+    pub fn new(internal_key: Pk, tree: Option<TapTree<Pk>>) -> Result<Self, Error> {
+        if let Some(ref t) = tree {
+            if t.taproot_depth() > TAPROOT_CONTROL_MAX_NODE_COUNT {
+                return Err(Error::Unexpected(format!(
+                    "Taptree exceeds the maximum depth of {}",
+                    TAPROOT_CONTROL_MAX_NODE_COUNT
+                )));
+            }
+        }
+        Ok(Self { internal_key, tree })
+    }
+
+    /// Get the internal key
+    pub fn internal_key(&self) -> &Pk {
+        &self.internal_key
+    }
+
+    /// Get the tree of leaf scripts, if any
+    pub fn taptree(&self) -> &Option<TapTree<Pk>> {
+        &self.tree
+    }
+
+    /// Get the descriptor without the checksum
+    pub fn to_string_no_checksum(&self) -> String {
+        match self.tree {
+            Some(ref tree) => format!("tr({},{})", self.internal_key, tree),
+            None => format!("tr({})", self.internal_key),
+        }
+    }
+
+    /// Checks whether the descriptor is safe.
+    pub fn sanity_check(&self) -> Result<(), Error> {
+        if let Some(ref tree) = self.tree {
+            for ms in tree.leaves_for_sanity_check() {
+                ms.sanity_check()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// sanity_check needs the leaves, but without requiring `ToPublicKey`.
+impl<Pk: MiniscriptKey> TapTree<Pk> {
+    fn leaves_for_sanity_check(&self) -> Vec<&Miniscript<Pk, Tap>> {
+        let mut out = vec![];
+        self.collect_for_sanity_check(&mut out);
+        out
+    }
+
+    fn collect_for_sanity_check<'a>(&'a self, out: &mut Vec<&'a Miniscript<Pk, Tap>>) {
+        match *self {
+            TapTree::Leaf(ref ms) => out.push(ms),
+            TapTree::Tree(ref left, ref right) => {
+                left.collect_for_sanity_check(out);
+                right.collect_for_sanity_check(out);
+            }
+        }
+    }
+}
+
+impl<Pk: MiniscriptKey + ToPublicKey> Tr<Pk> {
+    // The tweaked output key and its oddness, per BIP341: Q = P + tG,
+    // t = tagged_hash("TapTweak", P || merkle_root).
+    fn spend_info(&self) -> (XOnlyPublicKey, secp256k1::Parity) {
+        let internal_key = self.internal_key.to_public_key().to_x_only_pubkey();
+        let tweak = match self.tree {
+            Some(ref tree) => {
+                let root = tree.merkle_root();
+                tagged_hash("TapTweak", &[&internal_key.serialize(), &root[..]])
+            }
+            None => tagged_hash("TapTweak", &[&internal_key.serialize()]),
+        };
+        let scalar = Scalar::from_be_bytes(tweak.into_inner())
+            .expect("tagged hash is a valid scalar except with negligible probability");
+        let secp = Secp256k1::verification_only();
+        internal_key
+            .add_tweak(&secp, &scalar)
+            .expect("tap tweak is a valid addition except with negligible probability")
+    }
+
+    /// Obtain the corresponding script pubkey for this descriptor
+    /// Non failing verion of [`DescriptorTrait::script_pubkey`] for this descriptor
+    pub fn spk(&self) -> Script {
+        let (output_key, _parity) = self.spend_info();
+        Builder::new()
+            .push_opcode(OP_PUSHNUM_1)
+            .push_slice(&output_key.serialize())
+            .into_script()
+    }
+
+    /// Obtains the corresponding address for this descriptor.
+    pub fn address(&self, network: Network) -> Address {
+        let (output_key, _parity) = self.spend_info();
+        Address {
+            payload: Payload::WitnessProgram {
+                version: WitnessVersion::V1,
+                program: output_key.serialize().to_vec(),
+            },
+            network,
+        }
+    }
+}
+
+impl<Pk: MiniscriptKey> Liftable<Pk> for Tr<Pk> {
+    fn lift(&self) -> Result<semantic::Policy<Pk>, Error> {
+        match self.tree {
+            // A leaf-less Tr is pure key-path: the policy is just the internal key.
+            None => Ok(semantic::Policy::KeyHash(self.internal_key.to_pubkeyhash())),
+            Some(ref tree) => {
+                let mut leaf_policies = vec![];
+                for ms in tree.leaves_for_sanity_check() {
+                    leaf_policies.push(ms.lift()?);
+                }
+                leaf_policies.push(semantic::Policy::KeyHash(self.internal_key.to_pubkeyhash()));
+                Ok(semantic::Policy::Threshold(1, leaf_policies))
+            }
+        }
+    }
+}
+
+impl<Pk> FromTree for Tr<Pk>
+where
+    Pk: MiniscriptKey + FromStr,
+    Pk::Hash: FromStr,
+    <Pk as FromStr>::Err: ToString,
+    <<Pk as MiniscriptKey>::Hash as FromStr>::Err: ToString,
+{
+    fn from_tree(top: &expression::Tree) -> Result<Self, Error> {
+        if top.name == "tr" && (top.args.len() == 1 || top.args.len() == 2) {
+            let internal_key = expression::terminal(&top.args[0], |pk| Pk::from_str(pk))?;
+            let tree = match top.args.len() {
+                1 => None,
+                2 => Some(TapTree::from_tree(&top.args[1])?),
+                _ => unreachable!(),
+            };
+            Tr::new(internal_key, tree)
+        } else {
+            Err(Error::Unexpected(format!(
+                "{}({} args) while parsing tr descriptor",
+                top.name,
+                top.args.len(),
+            )))
+        }
+    }
+}
+
+impl<Pk: MiniscriptKey> fmt::Debug for Tr<Pk> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_string_no_checksum())
+    }
+}
+
+impl<Pk: MiniscriptKey> fmt::Display for Tr<Pk> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let desc = self.to_string_no_checksum();
+        let checksum = desc_checksum(&desc).map_err(|_| fmt::Error)?;
+        write!(f, "{}#{}", &desc, &checksum)
+    }
+}
+
+impl<Pk> FromStr for Tr<Pk>
+where
+    Pk: MiniscriptKey + FromStr,
+    Pk::Hash: FromStr,
+    <Pk as FromStr>::Err: ToString,
+    <<Pk as MiniscriptKey>::Hash as FromStr>::Err: ToString,
+{
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let desc_str = verify_checksum(s)?;
+        let top = expression::Tree::from_str(desc_str)?;
+        Tr::<Pk>::from_tree(&top)
+    }
+}
+
+impl<Pk: MiniscriptKey> DescriptorTrait<Pk> for Tr<Pk> {
+    fn script_pubkey(&self) -> Script
+    where
+        Pk: ToPublicKey,
+    {
+        self.spk()
+    }
+
+    fn unsigned_script_sig(&self) -> Script
+    where
+        Pk: ToPublicKey,
+    {
+        Script::new()
+    }
+
+    fn explicit_script(&self) -> Result<Script, Error>
+    where
+        Pk: ToPublicKey,
+    {
+        Err(Error::Unexpected(
+            "Tr descriptors have no single explicit script; use the leaf scripts instead"
+                .to_string(),
+        ))
+    }
+
+    fn get_satisfaction<S>(&self, satisfier: S) -> Result<(Vec<Vec<u8>>, Script), Error>
+    where
+        Pk: ToPublicKey,
+        S: Satisfier<Pk>,
+    {
+        // Prefer a key-path spend whenever the satisfier can produce one: it is
+        // always the cheapest possible witness (a single Schnorr signature).
+        if let Some(sig) = satisfier.lookup_tap_key_spend_sig() {
+            return Ok((vec![sig.to_vec()], Script::new()));
+        }
+
+        let tree = self.tree.as_ref().ok_or(Error::CouldNotSatisfy)?;
+        let internal_key = self.internal_key.to_public_key().to_x_only_pubkey();
+        let (_, parity) = self.spend_info();
+
+        let mut best: Option<(Vec<Vec<u8>>, usize)> = None;
+        for (ms, proof) in tree.leaves() {
+            let mut witness = match ms.satisfy(&satisfier) {
+                Ok(w) => w,
+                Err(_) => continue,
+            };
+            let script = ms.encode();
+
+            let mut control_block = Vec::with_capacity(33 + 32 * proof.len());
+            control_block.push(LEAF_VERSION_TAPSCRIPT | parity.to_u8());
+            control_block.extend(&internal_key.serialize());
+            for sibling in &proof {
+                control_block.extend(&sibling[..]);
+            }
+
+            let weight = witness.iter().map(|w| w.len()).sum::<usize>()
+                + script.len()
+                + control_block.len();
+
+            witness.push(script.into_bytes());
+            witness.push(control_block);
+
+            if best.as_ref().map_or(true, |&(_, best_weight)| weight < best_weight) {
+                best = Some((witness, weight));
+            }
+        }
+        best.map(|(w, _)| (w, Script::new())).ok_or(Error::CouldNotSatisfy)
+    }
+
+    fn get_satisfaction_mall<S>(&self, satisfier: S) -> Result<(Vec<Vec<u8>>, Script), Error>
+    where
+        Pk: ToPublicKey,
+        S: Satisfier<Pk>,
+    {
+        self.get_satisfaction(satisfier)
+    }
+
+    fn max_satisfaction_weight(&self) -> Result<usize, Error> {
+        let key_path_weight = 4 + 1 + 65; // scriptSig len + witness count + Schnorr sig push
+        let tree = match self.tree {
+            Some(ref tree) => tree,
+            None => return Ok(key_path_weight),
+        };
+
+        let mut max_weight = key_path_weight;
+        for (ms, proof) in tree.leaves() {
+            let script_size = ms.script_size();
+            let max_sat_elems = ms.max_satisfaction_witness_elements()?;
+            let max_sat_size = ms.max_satisfaction_size()?;
+            let control_block_size = 33 + 32 * proof.len();
+
+            let weight = 4 // scriptSig length byte
+                + varint_len(max_sat_elems + 2) // + script leaf + control block
+                + max_sat_size
+                + varint_len(script_size)
+                + script_size
+                + varint_len(control_block_size)
+                + control_block_size;
+            max_weight = cmp::max(max_weight, weight);
+        }
+        Ok(max_weight)
+    }
+
+    fn script_code(&self) -> Result<Script, Error>
+    where
+        Pk: ToPublicKey,
+    {
+        Err(Error::Unexpected(
+            "BIP341 does not define a scriptCode for Taproot spends".to_string(),
+        ))
+    }
+}
+
+impl<Pk: MiniscriptKey> ForEachKey<Pk> for Tr<Pk> {
+    fn for_each_key<'a, F: FnMut(ForEach<'a, Pk>) -> bool>(&'a self, mut pred: F) -> bool
+    where
+        Pk: 'a,
+        Pk::Hash: 'a,
+    {
+        if !pred(ForEach::Key(&self.internal_key)) {
+            return false;
+        }
+        match self.tree {
+            Some(ref tree) => tree
+                .leaves_for_sanity_check()
+                .into_iter()
+                .all(|ms| ms.for_each_key(&mut pred)),
+            None => true,
+        }
+    }
+}
+
+impl<P, Q> TranslatePk<P, Q> for Tr<P>
+where
+    P: MiniscriptKey,
+    Q: MiniscriptKey,
+{
+    type Output = Tr<Q>;
+
+    fn translate_pk<Fpk, Fpkh, E>(&self, mut fpk: Fpk, mut fpkh: Fpkh) -> Result<Self::Output, E>
+    where
+        Fpk: FnMut(&P) -> Result<Q, E>,
+        Fpkh: FnMut(&P::Hash) -> Result<Q::Hash, E>,
+    {
+        let internal_key = fpk(&self.internal_key)?;
+        let tree = match self.tree {
+            Some(ref tree) => Some(tree.translate_pk(&mut fpk, &mut fpkh)?),
+            None => None,
+        };
+        Ok(Tr { internal_key, tree })
+    }
+}
+
+impl<P: MiniscriptKey> TapTree<P> {
+    // Not a `TranslatePk` impl: the closures are reused across every leaf in the
+    // tree, so they must be taken by mutable reference rather than by value.
+    fn translate_pk<Q, Fpk, Fpkh, E>(&self, fpk: &mut Fpk, fpkh: &mut Fpkh) -> Result<TapTree<Q>, E>
+    where
+        Q: MiniscriptKey,
+        Fpk: FnMut(&P) -> Result<Q, E>,
+        Fpkh: FnMut(&P::Hash) -> Result<Q::Hash, E>,
+    {
+        match *self {
+            TapTree::Leaf(ref ms) => Ok(TapTree::Leaf(Rc::new(ms.translate_pk(fpk, fpkh)?))),
+            TapTree::Tree(ref left, ref right) => Ok(TapTree::Tree(
+                Rc::new(left.translate_pk(fpk, fpkh)?),
+                Rc::new(right.translate_pk(fpk, fpkh)?),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::PublicKey;
+
+    use super::*;
+
+    fn pk(hex: &str) -> PublicKey {
+        PublicKey::from_str(hex).unwrap()
+    }
+
+    // secp256k1's standard generator point, used purely as "some valid key" --
+    // none of these tests rely on its discrete log being known.
+    const INTERNAL_KEY: &str =
+        "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+    const LEAF_KEY_A: &str = "03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd";
+    const LEAF_KEY_B: &str = "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5";
+
+    #[test]
+    fn key_path_only_roundtrip() {
+        let internal_key = pk(INTERNAL_KEY);
+        let desc = Tr::<PublicKey>::new(internal_key, None).unwrap();
+        assert_eq!(desc.to_string_no_checksum(), format!("tr({})", internal_key));
+
+        let s = desc.to_string();
+        let rt = Tr::<PublicKey>::from_str(&s).unwrap();
+        assert_eq!(desc, rt);
+        assert!(rt.taptree().is_none());
+    }
+
+    #[test]
+    fn script_path_roundtrip() {
+        let internal_key = pk(INTERNAL_KEY);
+        let tree = TapTree::Tree(
+            Rc::new(TapTree::Leaf(Rc::new(
+                Miniscript::<PublicKey, Tap>::from_str(&format!("pk({})", pk(LEAF_KEY_A)))
+                    .unwrap(),
+            ))),
+            Rc::new(TapTree::Leaf(Rc::new(
+                Miniscript::<PublicKey, Tap>::from_str(&format!("pk({})", pk(LEAF_KEY_B)))
+                    .unwrap(),
+            ))),
+        );
+        let desc = Tr::new(internal_key, Some(tree)).unwrap();
+
+        let s = desc.to_string();
+        let rt = Tr::<PublicKey>::from_str(&s).unwrap();
+        assert_eq!(desc, rt);
+        assert!(rt.taptree().is_some());
+    }
+
+    #[test]
+    fn script_pubkey_is_tweaked_op_1_push32() {
+        let internal_key = pk(INTERNAL_KEY);
+        let desc = Tr::<PublicKey>::new(internal_key, None).unwrap();
+        let spk = desc.spk();
+
+        // OP_1 <32-byte tweaked output key>, per BIP341.
+        assert_eq!(spk.len(), 34);
+        assert_eq!(spk.as_bytes()[0], 0x51);
+        assert_eq!(spk.as_bytes()[1], 0x20);
+
+        // The output key must actually be tweaked: it should differ from the
+        // untweaked internal key's own x-only bytes.
+        let internal_xonly = internal_key.to_public_key().to_x_only_pubkey();
+        assert_ne!(&spk.as_bytes()[2..], &internal_xonly.serialize()[..]);
+    }
+
+    #[test]
+    fn taptree_depth_is_rejected_past_128() {
+        let internal_key = pk(INTERNAL_KEY);
+        let leaf = TapTree::Leaf(Rc::new(
+            Miniscript::<PublicKey, Tap>::from_str(&format!("pk({})", pk(LEAF_KEY_A))).unwrap(),
+        ));
+        let mut tree = leaf;
+        for _ in 0..(TAPROOT_CONTROL_MAX_NODE_COUNT + 1) {
+            tree = TapTree::Tree(
+                Rc::new(tree),
+                Rc::new(TapTree::Leaf(Rc::new(
+                    Miniscript::<PublicKey, Tap>::from_str(&format!("pk({})", pk(LEAF_KEY_B)))
+                        .unwrap(),
+                ))),
+            );
+        }
+        assert!(Tr::new(internal_key, Some(tree)).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+// Miniscript
+// Written in 2020 by rust-miniscript developers
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! # Script Contexts
+//!
+//! A [`ScriptContext`] fixes the rules a miniscript is checked and encoded
+//! under: the maximum script/stack sizes it must respect and how its
+//! top-level fragment is validated. `Segwitv0` is the context for scripts
+//! nested inside a P2WSH output.
+
+use std::fmt;
+
+use crate::policy::{semantic::Policy, Liftable};
+use crate::{Error, Miniscript, MiniscriptKey};
+
+/// The maximum size of a Segwit v0 witness script, per BIP141.
+const MAX_SCRIPT_SIZE: usize = 10_000;
+
+/// Extra parameters controlling how permissive miniscript parsing is.
+///
+/// By default only "sane" (non-malleable, safe) miniscripts with no raw `pkh`
+/// fragments are accepted; the `*_insane` family of constructors relaxes
+/// these heuristics so that arbitrary consensus-valid witness scripts can
+/// still be parsed, inspected and lifted, at the cost of no longer
+/// guaranteeing they're safe to use as a spending descriptor. The maximum
+/// witness script size is always enforced regardless of these toggles; stack
+/// element count and opcode count are not checked by
+/// [`ScriptContext::top_level_checks_ext`].
+///
+/// There is deliberately no "allow non-minimal pushes" toggle: every
+/// constructor in this crate builds its own script encoding (via
+/// [`Miniscript::encode`]), which is always minimal, so there is no code path
+/// through which a non-minimally-encoded miniscript could reach these checks
+/// in the first place.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub struct ExtParams {
+    /// Allow a top level that is unsafe (doesn't require a signature) or that
+    /// lacks a timelock, both of which are malleability/DoS heuristics rather
+    /// than consensus rules.
+    pub(crate) allow_unsafe: bool,
+    /// Allow raw `pkh(HASH)` fragments, whose preimage public key is unknown.
+    pub(crate) allow_raw_pkh: bool,
+}
+
+impl ExtParams {
+    /// The default, sane-only parameters: safe non-malleable top level
+    /// required, no raw `pkh`. This is what every `new`/`from_str`/`FromTree`
+    /// impl uses unless told otherwise.
+    pub fn new() -> Self {
+        Self {
+            allow_unsafe: false,
+            allow_raw_pkh: false,
+        }
+    }
+
+    /// The most permissive parameters: every experimental/malleability toggle
+    /// enabled. This is what the `*_insane` constructors use.
+    pub fn insane() -> Self {
+        Self {
+            allow_unsafe: true,
+            allow_raw_pkh: true,
+        }
+    }
+
+    /// Allow or disallow an unsafe (no-signature, no-timelock) top level.
+    pub fn unsafe_top_level(mut self, allow: bool) -> Self {
+        self.allow_unsafe = allow;
+        self
+    }
+
+    /// Allow or disallow raw `pkh(HASH)` fragments.
+    pub fn raw_pkh(mut self, allow: bool) -> Self {
+        self.allow_raw_pkh = allow;
+        self
+    }
+}
+
+impl Default for ExtParams {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Errors arising from a miniscript failing to meet its script context's restrictions.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum ScriptContextError {
+    /// This context requires compressed keys, but an uncompressed one was given.
+    CompressedOnly(String),
+}
+
+impl fmt::Display for ScriptContextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ScriptContextError::CompressedOnly(ref pk) => {
+                write!(f, "Uncompressed keys are not allowed in this context: {}", pk)
+            }
+        }
+    }
+}
+
+/// Trait fixing the rules a miniscript is checked and encoded under.
+pub trait ScriptContext:
+    fmt::Debug + Clone + Ord + PartialOrd + Eq + PartialEq + private::Sealed
+{
+    /// The length, in bytes, of `pk` when serialized under this context.
+    fn pk_len<Pk: MiniscriptKey>(pk: &Pk) -> usize {
+        if pk.is_uncompressed() {
+            65
+        } else {
+            33
+        }
+    }
+
+    /// Runs this context's sane-parsing checks on a top-level miniscript:
+    /// hard consensus resource limits, plus the sanity/non-malleability
+    /// heuristics.
+    fn top_level_checks<Pk: MiniscriptKey>(ms: &Miniscript<Pk, Self>) -> Result<(), Error>
+    where
+        Self: Sized,
+    {
+        Self::top_level_checks_ext(ms, ExtParams::new())
+    }
+
+    /// Like [`ScriptContext::top_level_checks`], but under the given
+    /// [`ExtParams`] instead of the sane-only defaults.
+    ///
+    /// Hard consensus resource limits (the maximum witness script size) are
+    /// enforced unconditionally. The remaining two checks are each gated on
+    /// their own `ext` toggle, independently of one another:
+    /// - `ext.allow_raw_pkh`: whether a raw `pkh(HASH)` fragment (a signature
+    ///   check against a hash whose preimage public key isn't known) may
+    ///   appear anywhere in the miniscript.
+    /// - `ext.allow_unsafe`: whether the malleability/safety heuristics
+    ///   checked by [`Miniscript::sanity_check`] (e.g. a top level that
+    ///   doesn't require a signature) may be violated.
+    ///
+    /// `ExtParams::insane()` sets both, so it can parse, inspect and lift any
+    /// consensus-valid script.
+    fn top_level_checks_ext<Pk: MiniscriptKey>(
+        ms: &Miniscript<Pk, Self>,
+        ext: ExtParams,
+    ) -> Result<(), Error>
+    where
+        Self: Sized,
+    {
+        if ms.script_size() > MAX_SCRIPT_SIZE {
+            return Err(Error::Unexpected(format!(
+                "Script size {} exceeds the maximum allowed size of {} bytes",
+                ms.script_size(),
+                MAX_SCRIPT_SIZE
+            )));
+        }
+        if !ext.allow_raw_pkh && contains_raw_pkh(&ms.lift()?) {
+            return Err(Error::Unexpected(
+                "raw pkh(HASH) fragments are not allowed".to_string(),
+            ));
+        }
+        if !ext.allow_unsafe {
+            ms.sanity_check()?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether `policy` contains a `pkh(HASH)` fragment (lifted to [`Policy::KeyHash`])
+/// anywhere in its tree.
+fn contains_raw_pkh<Pk: MiniscriptKey>(policy: &Policy<Pk>) -> bool {
+    match *policy {
+        Policy::KeyHash(..) => true,
+        Policy::And(ref subs) | Policy::Threshold(_, ref subs) => {
+            subs.iter().any(contains_raw_pkh)
+        }
+        Policy::Or(ref subs) => subs.iter().any(|(_, sub)| contains_raw_pkh(sub)),
+        Policy::Unsatisfiable
+        | Policy::Trivial
+        | Policy::Key(..)
+        | Policy::After(..)
+        | Policy::Older(..)
+        | Policy::Sha256(..)
+        | Policy::Hash256(..)
+        | Policy::Ripemd160(..)
+        | Policy::Hash160(..) => false,
+    }
+}
+
+/// The Segwit v0 (P2WSH / P2WPKH witness) script context.
+#[derive(Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Debug, Hash)]
+pub struct Segwitv0;
+
+impl ScriptContext for Segwitv0 {}
+
+impl private::Sealed for Segwitv0 {}
+
+mod private {
+    pub trait Sealed {}
+}
@@ -0,0 +1,55 @@
+// Miniscript
+// Written in 2020 by rust-miniscript developers
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! # Satisfying Witnesses
+//!
+//! A [`Satisfier`] answers the questions a miniscript's satisfaction search
+//! needs to ask of the outside world: which keys can sign, and (for Taproot)
+//! whether a key-path spend signature is available. Descriptor
+//! `get_satisfaction` implementations are generic over `S: Satisfier<Pk>` so
+//! that the same miniscript can be satisfied against a hot wallet, a hardware
+//! signer, a PSBT, or anything else able to answer these lookups.
+//!
+//! Every method defaults to returning `None`, so a satisfier only needs to
+//! implement the handful of lookups that are actually relevant to it.
+
+use bitcoin::{EcdsaSig, SchnorrSig};
+
+use crate::MiniscriptKey;
+
+/// A satisfier provides the data needed to turn a miniscript into a concrete,
+/// spending witness.
+pub trait Satisfier<Pk: MiniscriptKey> {
+    /// Looks up the ECDSA signature for `pk`, if available.
+    fn lookup_ecdsa_sig(&self, _pk: &Pk) -> Option<EcdsaSig> {
+        None
+    }
+
+    /// Looks up the key-path-spend Schnorr signature for a Taproot output's
+    /// tweaked output key, if available. A key-path spend is always preferred
+    /// over a script-path spend when present, since it produces the smallest
+    /// possible witness (a single signature, no leaf script or control block).
+    fn lookup_tap_key_spend_sig(&self) -> Option<SchnorrSig> {
+        None
+    }
+}
+
+impl<Pk: MiniscriptKey, S: Satisfier<Pk> + ?Sized> Satisfier<Pk> for &S {
+    fn lookup_ecdsa_sig(&self, pk: &Pk) -> Option<EcdsaSig> {
+        (**self).lookup_ecdsa_sig(pk)
+    }
+
+    fn lookup_tap_key_spend_sig(&self) -> Option<SchnorrSig> {
+        (**self).lookup_tap_key_spend_sig()
+    }
+}